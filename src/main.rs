@@ -1,6 +1,8 @@
 extern crate bio;
 use bio::io::fasta;
+use flate2::read::MultiGzDecoder;
 use std::collections::HashMap;
+use std::fs::File;
 use std::io;
 use std::io::Write;
 use std::env;
@@ -13,8 +15,59 @@ use std::process;
 // So... TTT = 0, TTC = 1, TTA = 2, ... , GGC = 61, GGA = 62, GGG = 63
 const GENETIC_CODE: &str = "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
 
-// ERR_BAD_NT is an error value for an invalid nucleotide 
-const ERR_BAD_NT: usize = 99; 
+// ERR_BAD_NT is an error value for an invalid nucleotide
+const ERR_BAD_NT: usize = 99;
+
+// A translation table identified by its NCBI genetic code ID. 'codes' holds
+// the 64 amino acids in the same T,C,A,G ordering as GENETIC_CODE; 'starts'
+// lists the codons that act as a Met start in this table, since tables differ
+// here (e.g. table 11 also allows GTG and TTG as starts).
+struct GeneticCode {
+    id: usize,
+    codes: String,
+    starts: Vec<String>,
+}
+
+impl GeneticCode {
+    // build a preloaded NCBI table by ID. Unknown IDs fall back to the
+    // standard code (table 1).
+    fn from_table(id: usize) -> GeneticCode {
+        // 'used_id' is the table actually applied; an unknown id falls back to
+        // the standard code (1), so we record 1 rather than the bogus request.
+        let (used_id, codes, starts): (usize, &str, &[&str]) = match id {
+            // vertebrate mitochondrial
+            2 => (
+                2,
+                "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+                &["ATT", "ATC", "ATA", "ATG", "GTG"],
+            ),
+            // yeast mitochondrial
+            3 => (
+                3,
+                "FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+                &["ATA", "ATG", "GTG"],
+            ),
+            // bacterial, archaeal and plant plastid
+            11 => (11, GENETIC_CODE, &["TTG", "CTG", "ATT", "ATC", "ATA", "ATG", "GTG"]),
+            // the standard code
+            _ => (1, GENETIC_CODE, &["TTG", "CTG", "ATG"]),
+        };
+        return GeneticCode {
+            id: used_id,
+            codes: codes.to_string(),
+            starts: starts.iter().map(|s| s.to_string()).collect(),
+        };
+    }
+
+    // does 'codon' act as a start codon in this table?
+    fn is_start(&self, codon: &str) -> bool {
+        return self.starts.iter().any(|s| s == codon);
+    }
+}
+
+// default peptide-length threshold (in residues): an ORF is reported only if
+// its peptide is longer than this
+const MIN_ORF_LENGTH: usize = 2;
 
 // enumeration of genetic sequence types
 enum SeqType {
@@ -32,7 +85,7 @@ enum Translation {
 // given an input 'char', return a base equivalent
 fn lookup(x: char) -> usize {
     match x {
-        'T' => return 0,
+        'T' | 'U' => return 0, // U (RNA) is equivalent to T (DNA)
         'C' => return 1,
         'A' => return 2,
         'G' => return 3,
@@ -41,7 +94,7 @@ fn lookup(x: char) -> usize {
 }
 
 // translate a codon into its corresponding amino acid
-fn translate(triplet: &str, t: Translation) -> String {
+fn translate(triplet: &str, code: &GeneticCode, t: Translation) -> String {
     let three_letter_code: HashMap<char, &str> = [
     ('A', "Ala"), 
     ('B', "???"), 
@@ -86,7 +139,7 @@ fn translate(triplet: &str, t: Translation) -> String {
 
     let index: usize = (codon[0] * 16) + (codon[1] * 4) + codon[2];
     // translate the codon into single-letter code
-    let c = GENETIC_CODE.chars().nth(index).unwrap();
+    let c = code.codes.chars().nth(index).unwrap();
     match t {
         Translation::OneLetter => return c.to_string(),
         Translation::ThreeLetter => return three_letter_code[&c].to_string(),
@@ -104,14 +157,195 @@ fn reverse_complement(s: &str) -> String {
 
     let mut rev_comp = String::new();
 
-    // iterate over the sequence in reverse, and add its complement to rev_comp
+    // iterate over the sequence in reverse, and add its complement to rev_comp.
+    // soft-masked lowercase bases are complemented as their uppercase form;
+    // any other base outside A/C/G/T (e.g. 'N') is not in the table, so map it
+    // to 'N' rather than panicking.
     for base in s.chars().rev() {
-        rev_comp.push(complements[&base]);
+        rev_comp.push(*complements.get(&base.to_ascii_uppercase()).unwrap_or(&'N'));
     }
 
     return rev_comp;
 }
 
+// a single open reading frame discovered by the six-frame scan
+struct Orf {
+    strand: char,   // '+' (forward strand) or '-' (reverse_complement)
+    frame: usize,   // reading-frame offset, 0, 1 or 2
+    start: usize,   // 1-based start coordinate on the forward strand
+    end: usize,     // 1-based end coordinate on the forward strand
+    peptide: String,
+}
+
+// scan all six reading frames of 's' and return the ORFs whose peptide is
+// longer than 'min_length' residues. The three forward frames are walked on
+// the sequence itself; the three reverse frames are walked on its
+// reverse_complement and their coordinates are mapped back onto the forward
+// strand (a reverse-strand position 'pos' corresponds to 'seq_len - 1 - pos').
+fn find_orfs(s: &str, code: &GeneticCode, min_length: usize) -> Vec<Orf> {
+    let mut orfs = Vec::new();
+    let seq_len = s.len();
+    let rev_comp = reverse_complement(s);
+
+    for &strand in ['+', '-'].iter() {
+        let strand_seq = match strand {
+            '+' => s,
+            _ => &rev_comp,
+        };
+
+        for frame in 0..3 {
+            let mut peptide = String::new();
+            let mut orf_start = 0;
+            let mut in_orf = false;
+            let mut pos = frame;
+
+            while pos + 3 <= seq_len {
+                let codon = &strand_seq[pos..pos + 3];
+
+                // an unknown base (e.g. 'N') breaks the current reading frame
+                if codon.chars().any(|b| lookup(b) == ERR_BAD_NT) {
+                    peptide.clear();
+                    in_orf = false;
+                    pos += 3;
+                    continue;
+                }
+
+                // a start codon opens a new ORF
+                let mut just_started = false;
+                if !in_orf && code.is_start(codon) {
+                    in_orf = true;
+                    orf_start = pos;
+                    just_started = true;
+                }
+
+                let residue = translate(codon, code, Translation::OneLetter);
+                if in_orf {
+                    if residue == "*" {
+                        // a stop closes the ORF; emit it if it is long enough
+                        if peptide.len() > min_length {
+                            let (start, end) = match strand {
+                                '+' => (orf_start + 1, pos + 3),
+                                _ => (seq_len - (pos + 2), seq_len - orf_start),
+                            };
+                            orfs.push(Orf {
+                                strand,
+                                frame,
+                                start,
+                                end,
+                                peptide: peptide.clone(),
+                            });
+                        }
+                        peptide.clear();
+                        in_orf = false;
+                    } else if just_started {
+                        // the initiator tRNA inserts fMet regardless of the
+                        // actual start codon (e.g. GTG/TTG), so emit Met here
+                        peptide.push('M');
+                    } else {
+                        peptide.push_str(&residue);
+                    }
+                }
+
+                pos += 3;
+            }
+        }
+    }
+
+    return orfs;
+}
+
+// fraction of G+C bases in 's'. Bases that 'lookup' does not recognise
+// (e.g. 'N') are excluded from both the numerator and the denominator.
+fn gc_fraction(s: &str) -> f64 {
+    let mut gc = 0;
+    let mut total = 0;
+    for base in s.chars() {
+        match lookup(base) {
+            ERR_BAD_NT => {}
+            1 | 3 => {
+                // C = 1, G = 3
+                gc += 1;
+                total += 1;
+            }
+            _ => total += 1,
+        }
+    }
+    if total == 0 {
+        return 0.0;
+    }
+    return gc as f64 / total as f64;
+}
+
+// print a tab-separated sliding-window GC report: each window's 1-based start
+// coordinate and GC percentage, a simple format suitable for plotting
+fn gc_windows(s: &str, window: usize, step: usize) {
+    // a zero step would loop forever, and a zero-width window holds no bases
+    if window == 0 || step == 0 {
+        println!("--window and --step must both be greater than 0");
+        return;
+    }
+    println!("start\tgc_percent");
+    let mut pos = 0;
+    while pos + window <= s.len() {
+        let frac = gc_fraction(&s[pos..pos + window]);
+        println!("{}\t{:.1}", pos + 1, frac * 100.0);
+        pos += step;
+    }
+}
+
+// does the IUPAC ambiguity code 'code' match the concrete base 'base'?
+// (e.g. N matches anything, R matches A/G, Y matches C/T)
+fn iupac_match(code: char, base: char) -> bool {
+    let set = match code.to_ascii_uppercase() {
+        'A' => "A",
+        'C' => "C",
+        'G' => "G",
+        'T' => "T",
+        'R' => "AG",
+        'Y' => "CT",
+        'S' => "GC",
+        'W' => "AT",
+        'K' => "GT",
+        'M' => "AC",
+        'B' => "CGT",
+        'D' => "AGT",
+        'H' => "ACT",
+        'V' => "ACG",
+        'N' => "ACGT",
+        _ => return false,
+    };
+    return set.contains(base.to_ascii_uppercase());
+}
+
+// return the 0-based offsets at which 'motif' (which may contain IUPAC
+// ambiguity codes) matches 's'
+fn motif_matches(s: &str, motif: &str) -> Vec<usize> {
+    let seq: Vec<char> = s.chars().collect();
+    let pat: Vec<char> = motif.chars().collect();
+    let mut hits = Vec::new();
+    if pat.is_empty() || pat.len() > seq.len() {
+        return hits;
+    }
+    for start in 0..=seq.len() - pat.len() {
+        if pat.iter().enumerate().all(|(i, &c)| iupac_match(c, seq[start + i])) {
+            hits.push(start);
+        }
+    }
+    return hits;
+}
+
+// transcribe a DNA sequence into its mRNA form (T -> U; other bases unchanged)
+fn transcribe(s: &str) -> String {
+    let mut rna = String::new();
+    for base in s.chars() {
+        match base {
+            'T' => rna.push('U'),
+            other => rna.push(other),
+        }
+    }
+    return rna;
+}
+
 // print a pretty sequence, 72 bases per line, plus base numbering
 // s: sequence
 // t: sequence type (DNA, Protein1 or Protein3)
@@ -136,27 +370,70 @@ fn print_seq(s: &str, t: SeqType) {
     }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut filename = "sequence.fasta";
-
-    // the user can provide another FASTA file on the command line
-    let args: Vec<String> = env::args().collect();
-    if args.len() > 1 {
-        filename = &args[1];
-    }
+// open a FASTA file, transparently decompressing '.gz' input. MultiGzDecoder
+// also copes with multi-member gzip files produced by tools like bgzip.
+fn open_reader(filename: &str) -> Result<fasta::Reader<io::BufReader<Box<dyn io::Read>>>, Box<dyn std::error::Error>> {
     if !std::path::Path::new(filename).exists() {
         println!("File '{}' does not exist.", filename);
         process::exit(1);
     }
-
     println!("Reading FASTA records from file '{}'...", filename);
-    let reader = fasta::Reader::from_file(filename)?;
+    let input: Box<dyn io::Read> = if filename.ends_with(".gz") {
+        Box::new(MultiGzDecoder::new(File::open(filename)?))
+    } else {
+        Box::new(File::open(filename)?)
+    };
+    return Ok(fasta::Reader::new(input));
+}
+
+// 'translate' subcommand: report GC content and the six-frame ORFs for each
+// record, and offer the interactive gene browser for the NC_005816.1 plasmid.
+fn cmd_translate(
+    filename: &str,
+    code: &GeneticCode,
+    window: Option<usize>,
+    step: Option<usize>,
+    rna: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Using genetic code table {}", code.id);
+    let reader = open_reader(filename)?;
 
     for record in reader.records() {
         let record = record.expect("Error during FASTA record parsing");
         println!("\nSequence ID: {}", record.id());
         println!("Sequence description:\n{}", record.desc().unwrap());
 
+        // convert the sequence record into a String
+        let seq = String::from_utf8(record.seq().to_vec())?;
+
+        // report overall GC content, plus a sliding-window profile on request
+        println!("\nOverall GC content: {:.1}%", gc_fraction(&seq) * 100.0);
+        if let Some(w) = window {
+            // default the step to the window width (non-overlapping windows)
+            gc_windows(&seq, w, step.unwrap_or(w));
+        }
+
+        // optionally display the mRNA strand alongside the translation
+        if rna {
+            println!("\nmRNA sequence:");
+            print_seq(&transcribe(&seq), SeqType::DNA);
+        }
+
+        // scan all six reading frames and report the open reading frames
+        let orfs = find_orfs(&seq, code, MIN_ORF_LENGTH);
+        println!("\nFound {} ORFs (longer than {} residues):", orfs.len(), MIN_ORF_LENGTH);
+        for orf in &orfs {
+            println!(
+                "{} frame {} {}..{} ({} aa)",
+                orf.strand,
+                orf.frame,
+                orf.start,
+                orf.end,
+                orf.peptide.len()
+            );
+            print_seq(&orf.peptide, SeqType::Protein1);
+        }
+
         if record.id() == "NC_005816.1" {
             println!("\nFrom 'https://www.ncbi.nlm.nih.gov/nuccore/NC_005816',");
             println!("we know that this piece of DNA encodes 9 genes.\n");
@@ -223,8 +500,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             let n_codons = s.len()/3;
             for i in 0..n_codons {
                 let codon = &s[i*3..(i*3)+3]; // take a 3-base slice of the sequence
-                peptide1.push_str(&translate(&codon, Translation::OneLetter)); // translate and add to the string
-                peptide3.push_str(&translate(&codon, Translation::ThreeLetter)); // translate and add to the string
+                peptide1.push_str(&translate(&codon, code, Translation::OneLetter)); // translate and add to the string
+                peptide3.push_str(&translate(&codon, code, Translation::ThreeLetter)); // translate and add to the string
             }
             println!("One-letter code:");
             print_seq(&peptide1, SeqType::Protein1);
@@ -234,7 +511,137 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    Ok(()) 
+    return Ok(());
+}
+
+// 'revcomp' subcommand: print the reverse complement of each record.
+fn cmd_revcomp(filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let reader = open_reader(filename)?;
+    for record in reader.records() {
+        let record = record.expect("Error during FASTA record parsing");
+        let seq = String::from_utf8(record.seq().to_vec())?;
+        println!("\nSequence ID: {}", record.id());
+        println!("Reverse complement:");
+        print_seq(&reverse_complement(&seq), SeqType::DNA);
+    }
+    return Ok(());
+}
+
+// 'grep' subcommand: print the header of every record whose ID, description
+// or sequence contains 'pattern'.
+fn cmd_grep(filename: &str, pattern: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let reader = open_reader(filename)?;
+    for record in reader.records() {
+        let record = record.expect("Error during FASTA record parsing");
+        let desc = record.desc().unwrap_or("");
+        let seq = String::from_utf8(record.seq().to_vec())?;
+        if record.id().contains(pattern) || desc.contains(pattern) || seq.contains(pattern) {
+            println!(">{} {}", record.id(), desc);
+        }
+    }
+    return Ok(());
+}
+
+// 'search' subcommand: report every hit of a nucleotide motif (with IUPAC
+// ambiguity codes) on both strands of each record, as record ID, 1-based
+// forward-strand position and strand.
+fn cmd_search(filename: &str, motif: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let reader = open_reader(filename)?;
+    let mlen = motif.chars().count();
+    for record in reader.records() {
+        let record = record.expect("Error during FASTA record parsing");
+        let id = record.id().to_string();
+        let seq = String::from_utf8(record.seq().to_vec())?;
+        let seq_len = seq.chars().count();
+
+        // forward strand
+        for pos in motif_matches(&seq, motif) {
+            println!("{}\t{}\t+", id, pos + 1);
+        }
+        // reverse strand: map the reverse_complement offset back to the
+        // forward strand (a hit at offset 'pos' covers pos..pos+mlen)
+        let rc = reverse_complement(&seq);
+        for pos in motif_matches(&rc, motif) {
+            println!("{}\t{}\t-", id, seq_len - pos - mlen + 1);
+        }
+    }
+    return Ok(());
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut table_id = 11;
+    let mut window: Option<usize> = None;
+    let mut step: Option<usize> = None;
+    let mut rna = false;
+    let mut positional: Vec<String> = Vec::new();
+
+    // flags select the genetic code table and GC-window parameters; the
+    // positional arguments are the subcommand and its operands
+    let args: Vec<String> = env::args().collect();
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--table" => {
+                i += 1;
+                table_id = args
+                    .get(i)
+                    .and_then(|a| a.parse::<usize>().ok())
+                    .unwrap_or_else(|| {
+                        println!("Invalid --table argument");
+                        process::exit(1);
+                    });
+            }
+            "--window" => {
+                i += 1;
+                window = args.get(i).and_then(|a| a.parse::<usize>().ok());
+            }
+            "--step" => {
+                i += 1;
+                step = args.get(i).and_then(|a| a.parse::<usize>().ok());
+            }
+            "--rna" => rna = true,
+            other => positional.push(other.to_string()),
+        }
+        i += 1;
+    }
+
+    // the first positional selects the subcommand; default to 'translate'
+    let command = positional.first().cloned().unwrap_or_else(|| "translate".to_string());
+    let code = GeneticCode::from_table(table_id);
+
+    match command.as_str() {
+        "translate" => {
+            let filename = positional.get(1).map(|s| s.as_str()).unwrap_or("sequence.fasta");
+            cmd_translate(filename, &code, window, step, rna)?;
+        }
+        "revcomp" => {
+            let filename = positional.get(1).map(|s| s.as_str()).unwrap_or("sequence.fasta");
+            cmd_revcomp(filename)?;
+        }
+        "grep" => {
+            let pattern = positional.get(1).unwrap_or_else(|| {
+                println!("Usage: grep <pattern> [file]");
+                process::exit(1);
+            });
+            let filename = positional.get(2).map(|s| s.as_str()).unwrap_or("sequence.fasta");
+            cmd_grep(filename, pattern)?;
+        }
+        "search" => {
+            let motif = positional.get(1).unwrap_or_else(|| {
+                println!("Usage: search <motif> [file]");
+                process::exit(1);
+            });
+            let filename = positional.get(2).map(|s| s.as_str()).unwrap_or("sequence.fasta");
+            cmd_search(filename, motif)?;
+        }
+        other => {
+            println!("Unknown subcommand '{}'", other);
+            println!("Usage: <translate|revcomp|grep|search> [options] [file]");
+            process::exit(1);
+        }
+    }
+
+    return Ok(());
 }
 
 
@@ -245,22 +652,39 @@ mod tests {
 
     #[test]
     fn test_translate_atg() {
-        assert_eq!(translate("ATG", Translation::ThreeLetter), "Met");
+        let code = GeneticCode::from_table(11);
+        assert_eq!(translate("ATG", &code, Translation::ThreeLetter), "Met");
     }
 
     #[test]
     fn test_translate_tag() {
-        assert_eq!(translate("TAG", Translation::ThreeLetter), "***");
+        let code = GeneticCode::from_table(11);
+        assert_eq!(translate("TAG", &code, Translation::ThreeLetter), "***");
     }
 
     #[test]
     fn test_translate_ttt() {
-        assert_eq!(translate("TTT", Translation::OneLetter), "F");
+        let code = GeneticCode::from_table(11);
+        assert_eq!(translate("TTT", &code, Translation::OneLetter), "F");
+    }
+
+    #[test]
+    fn test_transcribe() {
+        assert_eq!(transcribe("ATGC"), "AUGC");
     }
 
     #[test]
-    fn bad_translation_atg() {
-        assert_eq!(translate("ATG", Translation::ThreeLetter), "Phe");
+    fn test_translate_rna_codon() {
+        // an RNA codon (AUG) should translate just like its DNA form (ATG)
+        let code = GeneticCode::from_table(11);
+        assert_eq!(translate("AUG", &code, Translation::ThreeLetter), "Met");
+    }
+
+    #[test]
+    fn test_table2_aga_is_stop() {
+        // AGA is Arg in the standard code but a stop in vertebrate mito (table 2)
+        let code = GeneticCode::from_table(2);
+        assert_eq!(translate("AGA", &code, Translation::OneLetter), "*");
     }
 
     #[test]
@@ -272,4 +696,48 @@ mod tests {
     fn test_reverse_complement2() {
         assert_eq!(reverse_complement("AAAGGGAAATTT"), "AAATTTCCCTTT")
     }
+
+    #[test]
+    fn test_gc_fraction() {
+        assert_eq!(gc_fraction("GGCC"), 1.0);
+        assert_eq!(gc_fraction("ATGC"), 0.5);
+        // unrecognised bases are ignored
+        assert_eq!(gc_fraction("GCNN"), 1.0);
+    }
+
+    #[test]
+    fn test_iupac_match() {
+        assert!(iupac_match('N', 'A'));
+        assert!(iupac_match('R', 'G'));
+        assert!(!iupac_match('R', 'C'));
+        assert!(iupac_match('Y', 'T'));
+    }
+
+    #[test]
+    fn test_motif_matches_iupac() {
+        // 'GCN' should match GCA, GCC, GCG and GCT
+        assert_eq!(motif_matches("GCAGCTGGG", "GCN"), vec![0, 3]);
+        // an exact motif
+        assert_eq!(motif_matches("AATTAA", "TT"), vec![2]);
+    }
+
+    #[test]
+    fn test_find_orfs_forward() {
+        // ATG GCA GCA TAA encodes Met-Ala-Ala then a stop on the + strand
+        let code = GeneticCode::from_table(11);
+        let orfs = find_orfs("ATGGCAGCATAA", &code, 2);
+        assert_eq!(orfs.len(), 1);
+        assert_eq!(orfs[0].strand, '+');
+        assert_eq!(orfs[0].peptide, "MAA");
+        assert_eq!((orfs[0].start, orfs[0].end), (1, 12));
+    }
+
+    #[test]
+    fn test_find_orfs_alt_start_is_met() {
+        // GTG is an alternative start in table 11; the initiator must read as M
+        let code = GeneticCode::from_table(11);
+        let orfs = find_orfs("GTGGCATAA", &code, 1);
+        assert_eq!(orfs.len(), 1);
+        assert_eq!(orfs[0].peptide, "MA");
+    }
 }